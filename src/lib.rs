@@ -19,40 +19,146 @@ use strum::{EnumDiscriminants, IntoDiscriminant};
 
 /// Build the bundled SQLite sources, using the given [`Config`].
 pub fn build(location: Location, config: impl AsRef<Config>) -> Build {
-    let config = config.as_ref();
+    let mut config = config.as_ref().clone();
+
+    if targets_wasm() {
+        // wasm32-wasi/wasm32-unknown have no threads and no real filesystem
+        // (so no mmap), so force the settings that reflect that rather than
+        // trusting the caller's `Config` to get it right.
+        config.set(Setting::Threading(Threading::SingleThread));
+        config.set(Setting::Os(OsMode::Other));
+        config.set(Setting::MaxMmapSize(0));
+    }
+
+    let crypto = match config.get(SettingKey::Encryption) {
+        Some(Setting::Encryption(crypto)) => Some(crypto),
+        _ => None,
+    };
+    let vfs = config.vfs().cloned();
+    let extensions = config.extensions().to_vec();
 
     let mut compiler = cc::Build::new();
     compiler.file(location.input());
 
     config.apply(&mut compiler);
+
+    if let Some(vfs) = &vfs {
+        compiler.file(vfs);
+    }
+    if !extensions.is_empty() {
+        for extension in &extensions {
+            compiler.file(location.extension_source(*extension));
+            compiler.define(extension.enable_define(), None);
+        }
+
+        // `ext/init.c` registers each enabled extension's entry point with
+        // `sqlite3_auto_extension`, gated on the `SQLITE_SQUIRE_ENABLE_*`
+        // defines set above.
+        compiler.file(location.extension_init_source());
+        compiler.define("SQLITE_EXTRA_INIT", "squire_extra_init");
+    }
+
     compiler.warnings(false);
 
     compiler.out_dir(&location.dest);
     compiler.compile("sqlite3");
 
-    Build::new(location)
+    Build::new(location, crypto, vfs, extensions)
+}
+
+/// Whether the crate is being built for a WASM target (`wasm32-wasi` or
+/// `wasm32-unknown-unknown`), per `$CARGO_CFG_TARGET_ARCH`.
+fn targets_wasm() -> bool {
+    matches!(env::var("CARGO_CFG_TARGET_ARCH").as_deref(), Ok("wasm32"))
 }
 
 /// The output of [`Build`], including the [`Location`] SQLite was built into.
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub struct Build {
     location: Location,
+    crypto: Option<Crypto>,
+    link: Option<Link>,
+    vfs: Option<PathBuf>,
+    extensions: Vec<Extension>,
 }
 
 impl Build {
-    const fn new(location: Location) -> Build {
-        Self { location }
+    const fn new(
+        location: Location,
+        crypto: Option<Crypto>,
+        vfs: Option<PathBuf>,
+        extensions: Vec<Extension>,
+    ) -> Build {
+        Self {
+            location,
+            crypto,
+            link: None,
+            vfs,
+            extensions,
+        }
+    }
+
+    /// Link against a platform-provided SQLite library instead of compiling
+    /// `sqlite3.c` — e.g. Windows' built-in [`Link::winsqlite3`], or a
+    /// [`Link::system`] `libsqlite3`.
+    pub fn system(location: Location, link: Link) -> Build {
+        link.link();
+
+        Self {
+            location,
+            crypto: None,
+            link: Some(link),
+            vfs: None,
+            extensions: Vec::new(),
+        }
     }
 
-    /// The `.c` source files that need to be built (`sqlite3.c`).
+    /// The `.c` source files that need to be built — `sqlite3.c`, plus any
+    /// [VFS source](Config::set_vfs_source) and
+    /// [bundled extensions](Config::bundle_extension) (with the `ext/init.c`
+    /// shim that registers them) — or an empty iterator
+    /// if this [`Build`] links a system library via [`Build::system`].
     pub fn sources(&self) -> impl Iterator<Item = PathBuf> {
-        iter::once(self.input())
+        let mut sources = Vec::new();
+
+        if self.link.is_none() {
+            sources.push(self.input());
+            sources.extend(self.vfs.clone());
+            sources.extend(
+                self.extensions
+                    .iter()
+                    .map(|extension| self.location.extension_source(*extension)),
+            );
+            if !self.extensions.is_empty() {
+                sources.push(self.location.extension_init_source());
+            }
+        }
+
+        sources.into_iter()
+    }
+
+    /// The [extensions](Extension) statically compiled into this build via
+    /// [`Config::bundle_extension`].
+    pub fn extensions(&self) -> &[Extension] {
+        &self.extensions
     }
 
     /// The build [`Location`].
     pub const fn location(&self) -> &Location {
         &self.location
     }
+
+    /// The cryptographic backend linked into this build, if
+    /// [`Setting::Encryption`] was set.
+    pub const fn crypto(&self) -> Option<Crypto> {
+        self.crypto
+    }
+
+    /// The system library this build links against, if created with
+    /// [`Build::system`].
+    pub fn link(&self) -> Option<&Link> {
+        self.link.as_ref()
+    }
 }
 
 impl Deref for Build {
@@ -73,8 +179,20 @@ pub struct Location {
 impl Location {
     /// Create a build [`Location`] from `$CARGO_MANIFEST_DIR`.
     pub fn new(dest: impl Into<PathBuf>) -> Self {
+        Self::at("sqlite", dest)
+    }
+
+    /// Create a build [`Location`] pointing at the bundled [SQLCipher][]
+    /// amalgamation, for use with [`Setting::Encryption`].
+    ///
+    /// [SQLCipher]: https://www.zetetic.net/sqlcipher/
+    pub fn encrypted(dest: impl Into<PathBuf>) -> Self {
+        Self::at("sqlcipher", dest)
+    }
+
+    fn at(dir: &str, dest: impl Into<PathBuf>) -> Self {
         Self {
-            src: PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("sqlite"),
+            src: PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(dir),
             dest: dest.into(),
         }
     }
@@ -89,6 +207,18 @@ impl Location {
         self.src.join("sqlite3.h")
     }
 
+    /// The path to a bundled [`Extension`]'s `.c` source file.
+    pub fn extension_source(&self, extension: Extension) -> PathBuf {
+        self.src.join(extension.source())
+    }
+
+    /// The path to `ext/init.c`, the shim that registers bundled
+    /// [`Extension`]s' entry points via `SQLITE_EXTRA_INIT`. Only compiled
+    /// when at least one extension is bundled.
+    pub fn extension_init_source(&self) -> PathBuf {
+        self.src.join("ext").join("init.c")
+    }
+
     /// The build's target directory.
     pub fn dest(&self) -> PathBuf {
         self.dest.clone()
@@ -114,6 +244,8 @@ impl Default for Location {
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub struct Config {
     settings: HashMap<SettingKey, Setting>,
+    vfs: Option<PathBuf>,
+    extensions: Vec<Extension>,
 }
 
 impl Config {
@@ -124,6 +256,8 @@ impl Config {
                 .into_iter()
                 .map(|setting| (setting.discriminant(), setting))
                 .collect(),
+            vfs: None,
+            extensions: Vec::new(),
         }
     }
 
@@ -137,10 +271,45 @@ impl Config {
         self.settings.insert(setting.discriminant(), setting);
     }
 
+    /// Register an extra VFS (virtual file system) source file to compile
+    /// alongside `sqlite3.c` — e.g. a shim for [`Setting::Os(OsMode::Other)`]
+    /// targets like `wasm32-wasi`, which have no real filesystem.
+    pub fn set_vfs_source(&mut self, source: impl Into<PathBuf>) {
+        self.vfs = Some(source.into());
+    }
+
+    /// The extra VFS source file registered with [`Config::set_vfs_source`],
+    /// if any.
+    pub fn vfs(&self) -> Option<&PathBuf> {
+        self.vfs.as_ref()
+    }
+
+    /// Statically compile a SQLite [`Extension`] into the build and
+    /// auto-register it on `sqlite3_initialize`, so it's available without
+    /// `load_extension`.
+    pub fn bundle_extension(&mut self, extension: Extension) {
+        if !self.extensions.contains(&extension) {
+            self.extensions.push(extension);
+        }
+    }
+
+    /// The [extensions](Extension) registered with
+    /// [`Config::bundle_extension`].
+    pub fn extensions(&self) -> &[Extension] {
+        &self.extensions
+    }
+
     fn apply(&self, build: &mut cc::Build) {
         for setting in self.settings.values() {
             setting.apply(build);
         }
+
+        if let Some(Setting::Encryption(crypto)) = self.get(SettingKey::Encryption) {
+            // SQLCipher requires temporary files to live entirely in memory,
+            // so that plaintext page data never touches disk.
+            Setting::TemporaryStorage(TemporaryStorage::AlwaysMemory).apply(build);
+            crypto.link(build);
+        }
     }
 }
 
@@ -165,16 +334,21 @@ impl Default for Config {
             Setting::EnableAutomaticInitialize(true), // TODO
             Setting::EnableAutomaticReset(false),
             Setting::EnableBlobIo(false),
+            Setting::EnableBytecodeVirtualTable(false),
             Setting::EnableColumnDeclaredType(false),
             Setting::EnableDatabasePagesVirtualTable(false),
             Setting::EnableDatabaseStatisticsVirtualTable(false),
             Setting::EnableDatabaseUri(true),
             Setting::EnableDeprecated(false),
+            Setting::EnableExplainComments(false),
             Setting::EnableGetTable(false),
+            Setting::EnableMathFunctions(false),
             Setting::EnableMemoryManagement(true),
             Setting::EnableProgressCallback(false),
             Setting::EnableSharedCache(false),
+            Setting::EnableStatementVirtualTable(false),
             Setting::EnableTrace(false),
+            Setting::EnableUnlockNotify(false),
             Setting::EnableUtf16(false),
             Setting::EnableVirtualTables(true),
             Setting::EnableWriteAheadLog(true),
@@ -213,6 +387,14 @@ pub enum Setting {
     DefaultForeignKeys(bool),
     #[doc(alias = "SQLITE_DEFAULT_MEMSTATUS")]
     DefaultMemoryStatus(bool),
+    #[doc(alias = "SQLITE_DEFAULT_CACHE_SIZE")]
+    DefaultCacheSize(usize),
+    #[doc(alias = "SQLITE_DEFAULT_MMAP_SIZE")]
+    DefaultMmapSize(usize),
+    #[doc(alias = "SQLITE_DEFAULT_PAGE_SIZE")]
+    DefaultPageSize(usize),
+    #[doc(alias = "SQLITE_DEFAULT_WAL_AUTOCHECKPOINT")]
+    DefaultWalAutocheckpoint(usize),
     #[doc(alias = "SQLITE_USE_ALLOCA")]
     EnableAlloca(bool),
     #[doc(alias = "SQLITE_ENABLE_API_ARMOR")]
@@ -275,18 +457,30 @@ pub enum Setting {
     EnableSharedCache(bool),
     #[doc(alias = "SQLITE_SOUNDEX")]
     EnableSoundex(bool),
+    #[doc(alias = "SQLITE_ENABLE_MATH_FUNCTIONS")]
+    EnableMathFunctions(bool),
+    #[doc(alias = "SQLITE_ENABLE_STMTVTAB")]
+    EnableStatementVirtualTable(bool),
+    #[doc(alias = "SQLITE_ENABLE_BYTECODE_VTAB")]
+    EnableBytecodeVirtualTable(bool),
+    #[doc(alias = "SQLITE_ENABLE_EXPLAIN_COMMENTS")]
+    EnableExplainComments(bool),
     #[doc(alias = "SQLITE_OMIT_TCL_VARIABLE")]
     EnableTclVariables(bool),
     #[doc(alias = "SQLITE_OMIT_TEMPDB")]
     EnableTemporaryDatabase(bool),
     #[doc(alias = "SQLITE_OMIT_TRACE")]
     EnableTrace(bool),
+    #[doc(alias = "SQLITE_ENABLE_UNLOCK_NOTIFY")]
+    EnableUnlockNotify(bool),
     #[doc(alias = "SQLITE_OMIT_UTF16")]
     EnableUtf16(bool),
     #[doc(alias = "SQLITE_OMIT_VIRTUALTABLE")]
     EnableVirtualTables(bool),
     #[doc(alias = "SQLITE_OMIT_VIRTUALTABLE")]
     EnableWriteAheadLog(bool),
+    #[doc(alias = "SQLITE_HAS_CODEC")]
+    Encryption(Crypto),
     #[doc(alias = "SQLITE_OMIT_WAL")]
     LikeOperatorCaseSensitive(bool),
     #[doc(alias = "SQLITE_LIKE_DOESNT_MATCH_BLOBS")]
@@ -299,8 +493,14 @@ pub enum Setting {
     MaxExpressionDepth(usize),
     #[doc(alias = "SQLITE_JSON_MAX_DEPTH")]
     MaxJsonDepth(usize),
+    #[doc(alias = "SQLITE_MAX_MMAP_SIZE")]
+    MaxMmapSize(usize),
     #[doc(alias = "SQLITE_MAX_VARIABLE_NUMBER")]
     MaxVariables(usize),
+    #[doc(alias = "SQLITE_OS_UNIX")]
+    #[doc(alias = "SQLITE_OS_WIN")]
+    #[doc(alias = "SQLITE_OS_OTHER")]
+    Os(OsMode),
     #[doc(alias = "SQLITE_SECURE_DELETE")]
     SecureDelete(bool),
     #[doc(alias = "SQLITE_TEMP_STORE")]
@@ -327,6 +527,18 @@ impl Setting {
             Setting::DefaultMemoryStatus(enable) => {
                 self.set(build, "SQLITE_DEFAULT_MEMSTATUS", enable);
             }
+            Setting::DefaultCacheSize(size) => {
+                self.set(build, "SQLITE_DEFAULT_CACHE_SIZE", size);
+            }
+            Setting::DefaultMmapSize(size) => {
+                self.set(build, "SQLITE_DEFAULT_MMAP_SIZE", size);
+            }
+            Setting::DefaultPageSize(size) => {
+                self.set(build, "SQLITE_DEFAULT_PAGE_SIZE", size);
+            }
+            Setting::DefaultWalAutocheckpoint(pages) => {
+                self.set(build, "SQLITE_DEFAULT_WAL_AUTOCHECKPOINT", pages);
+            }
             Setting::DoubleQuotedStrings(DoubleQuotedStrings { in_ddl, in_dml }) => {
                 let value = match (in_ddl, in_dml) {
                     (true, true) => 3,
@@ -425,6 +637,18 @@ impl Setting {
             Setting::EnableSoundex(enable) => {
                 self.define(build, "SQLITE_SOUNDEX", enable);
             }
+            Setting::EnableMathFunctions(enable) => {
+                self.define(build, "SQLITE_ENABLE_MATH_FUNCTIONS", enable);
+            }
+            Setting::EnableStatementVirtualTable(enable) => {
+                self.define(build, "SQLITE_ENABLE_STMTVTAB", enable);
+            }
+            Setting::EnableBytecodeVirtualTable(enable) => {
+                self.define(build, "SQLITE_ENABLE_BYTECODE_VTAB", enable);
+            }
+            Setting::EnableExplainComments(enable) => {
+                self.define(build, "SQLITE_ENABLE_EXPLAIN_COMMENTS", enable);
+            }
             Setting::EnableStat4(enable) => {
                 self.define(build, "SQLITE_ENABLE_STAT4", enable);
             }
@@ -437,6 +661,9 @@ impl Setting {
             Setting::EnableTrace(enable) => {
                 self.define(build, "SQLITE_OMIT_TRACE", !enable);
             }
+            Setting::EnableUnlockNotify(enable) => {
+                self.define(build, "SQLITE_ENABLE_UNLOCK_NOTIFY", enable);
+            }
             Setting::EnableUtf16(enable) => {
                 self.define(build, "SQLITE_OMIT_UTF16", !enable);
             }
@@ -446,6 +673,9 @@ impl Setting {
             Setting::EnableWriteAheadLog(enable) => {
                 self.define(build, "SQLITE_OMIT_WAL", !enable);
             }
+            Setting::Encryption(_) => {
+                self.define(build, "SQLITE_HAS_CODEC", true);
+            }
             Setting::LikeOperatorCaseSensitive(enable) => {
                 self.define(build, "SQLITE_CASE_SENSITIVE_LIKE", enable);
             }
@@ -464,9 +694,21 @@ impl Setting {
             Setting::MaxJsonDepth(max) => {
                 self.set(build, "SQLITE_JSON_MAX_DEPTH", max);
             }
+            Setting::MaxMmapSize(size) => {
+                self.set(build, "SQLITE_MAX_MMAP_SIZE", size);
+            }
             Setting::MaxVariables(max) => {
                 self.set(build, "SQLITE_MAX_VARIABLE_NUMBER", max);
             }
+            Setting::Os(mode) => {
+                let name = match mode {
+                    OsMode::Unix => "SQLITE_OS_UNIX",
+                    OsMode::Windows => "SQLITE_OS_WIN",
+                    OsMode::Other => "SQLITE_OS_OTHER",
+                };
+
+                self.define(build, name, true);
+            }
             Setting::SecureDelete(enable) => {
                 self.define(build, "SQLITE_SECURE_DELETE", enable);
             }
@@ -531,6 +773,149 @@ pub enum Synchronous {
     Extra = 3,
 }
 
+/// Which `os_*.c` backend SQLite is compiled against. `Other` is for targets
+/// with no real OS filesystem, such as `wasm32-wasi`, and requires a VFS
+/// registered via [`Config::set_vfs_source`] (or `sqlite3_os_init`'s default
+/// stub implementation).
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum OsMode {
+    Unix,
+    Windows,
+    Other,
+}
+
+/// A cryptographic backend for [`Setting::Encryption`], providing the codec
+/// used by SQLCipher-style encrypted databases.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Crypto {
+    /// Link against OpenSSL's `libcrypto`.
+    OpenSsl,
+    /// Link against LibreSSL's `libcrypto`.
+    LibreSsl,
+    /// Use Apple's CommonCrypto, part of `libSystem` on macOS and iOS.
+    CommonCrypto,
+    /// Link against Mozilla NSS (`libnss3`).
+    Nss,
+}
+
+impl Crypto {
+    /// The environment variable a dependency's build script exports its
+    /// header directory under, if any (e.g. `openssl-sys` exports
+    /// `DEP_OPENSSL_INCLUDE`).
+    fn include_env(&self) -> Option<&'static str> {
+        match self {
+            Crypto::OpenSsl | Crypto::LibreSsl => Some("DEP_OPENSSL_INCLUDE"),
+            Crypto::CommonCrypto | Crypto::Nss => None,
+        }
+    }
+
+    /// The library name to pass to `cargo:rustc-link-lib`.
+    fn link_lib(&self) -> &'static str {
+        match self {
+            Crypto::OpenSsl | Crypto::LibreSsl => "crypto",
+            Crypto::CommonCrypto => "System",
+            Crypto::Nss => "nss3",
+        }
+    }
+
+    fn link(&self, build: &mut cc::Build) {
+        if let Some(dir) = self.include_env().and_then(env::var_os) {
+            build.include(dir);
+        }
+
+        println!("cargo:rustc-link-lib={}", self.link_lib());
+    }
+}
+
+/// A platform-provided SQLite library to link against, instead of compiling
+/// `sqlite3.c`. See [`Build::system`].
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Link {
+    library: &'static str,
+    min_version: (u8, u8, u8),
+    symbol_prefix: Option<&'static str>,
+}
+
+impl Link {
+    /// Link against Windows' built-in `winsqlite3.dll` (available since
+    /// Windows 10), as `libsqlite3-sys` does with its `winsqlite3` feature.
+    /// Its exports are prefixed `winsqlite3_` rather than `sqlite3_`, and it
+    /// only guarantees compatibility with SQLite 3.18.0.
+    pub const fn winsqlite3() -> Self {
+        Self {
+            library: "winsqlite3",
+            min_version: (3, 18, 0),
+            symbol_prefix: Some("winsqlite3_"),
+        }
+    }
+
+    /// Link against a system-installed `libsqlite3`, found via the platform's
+    /// usual library search path (or `pkg-config`, if the build script is set
+    /// up to invoke it).
+    pub const fn system() -> Self {
+        Self {
+            library: "sqlite3",
+            min_version: (3, 0, 0),
+            symbol_prefix: None,
+        }
+    }
+
+    /// The minimum SQLite version this library is guaranteed to provide.
+    pub const fn min_version(&self) -> (u8, u8, u8) {
+        self.min_version
+    }
+
+    /// The prefix this library's exported symbols use, if not the usual
+    /// `sqlite3_` prefix.
+    pub const fn symbol_prefix(&self) -> Option<&'static str> {
+        self.symbol_prefix
+    }
+
+    fn link(&self) {
+        println!("cargo:rustc-link-lib={}", self.library);
+    }
+}
+
+/// A SQLite extension bundled with this crate that can be statically
+/// compiled in via [`Config::bundle_extension`], making it available without
+/// runtime `load_extension`.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Extension {
+    /// The `csvtab` virtual table, for querying CSV files as SQL tables.
+    Csv,
+    /// The `series` virtual table (`generate_series`).
+    Series,
+    /// The `carray`/`array` virtual table, for binding a Rust slice as a
+    /// table-valued parameter.
+    Carray,
+    /// The `regexp` extension, adding a `REGEXP` SQL function.
+    Regexp,
+}
+
+impl Extension {
+    /// The extension's `.c` source file, relative to the build
+    /// [`Location`]'s source directory.
+    fn source(&self) -> &'static str {
+        match self {
+            Extension::Csv => "ext/csv.c",
+            Extension::Series => "ext/series.c",
+            Extension::Carray => "ext/carray.c",
+            Extension::Regexp => "ext/regexp.c",
+        }
+    }
+
+    /// The `-D` define that gates this extension's entry point in
+    /// `ext/init.c`.
+    fn enable_define(&self) -> &'static str {
+        match self {
+            Extension::Csv => "SQLITE_SQUIRE_ENABLE_CSV",
+            Extension::Series => "SQLITE_SQUIRE_ENABLE_SERIES",
+            Extension::Carray => "SQLITE_SQUIRE_ENABLE_CARRAY",
+            Extension::Regexp => "SQLITE_SQUIRE_ENABLE_REGEXP",
+        }
+    }
+}
+
 trait SettingValue {
     fn apply(&self, build: &mut cc::Build, name: &'static str);
 }